@@ -0,0 +1,122 @@
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use rss::Item;
+
+use crate::types::Episode;
+
+/// Parses the value of an `<itunes:duration>` tag into a total number
+/// of seconds, used to populate `Episode.duration` when building an
+/// episode from a feed item.
+///
+/// Feeds express duration inconsistently: some give a bare number of
+/// seconds, others `MM:SS`, others `HH:MM:SS`. This accepts all three,
+/// treating the rightmost group as seconds and promoting the earlier
+/// groups to minutes and hours as they appear. Returns `None` if the
+/// value is empty or doesn't match any of those shapes.
+pub fn parse_duration(raw: &str) -> Option<i32> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+
+    let re = Regex::new(r"^(?:(\d+):)?(?:(\d+):)?(\d+)$").unwrap();
+    let caps = re.captures(trimmed)?;
+
+    let seconds: i64 = caps.get(3)?.as_str().parse().ok()?;
+    let (hours, minutes): (i64, i64) = match (caps.get(1), caps.get(2)) {
+        (Some(h), Some(m)) => (h.as_str().parse().ok()?, m.as_str().parse().ok()?),
+        (Some(m), None) => (0, m.as_str().parse().ok()?),
+        (None, None) => (0, 0),
+        (None, Some(_)) => unreachable!("regex can't capture group 2 without group 1"),
+    };
+
+    // Use checked arithmetic rather than plain `*`/`+` -- a malformed
+    // feed can supply an absurdly long (but still `i64`-parseable)
+    // digit group, and we'd rather clamp to `i32::MAX` than panic on
+    // overflow (debug builds) or wrap to garbage (release builds).
+    let total = hours.checked_mul(3600)
+        .and_then(|h| minutes.checked_mul(60).and_then(|m| h.checked_add(m)))
+        .and_then(|hm| hm.checked_add(seconds))
+        .unwrap_or(i64::MAX);
+    Some(total.min(i32::MAX as i64) as i32)
+}
+
+/// Builds an `Episode` from a single `<item>` in a podcast's RSS feed.
+/// Called once per item while `spawn_feed_checker` assembles the full
+/// episode list for a podcast.
+pub fn episode_from_item(item: &Item) -> Episode {
+    let url = item.enclosure()
+        .map(|enclosure| enclosure.url().to_string())
+        .or_else(|| item.link().map(|link| link.to_string()))
+        .unwrap_or_default();
+
+    let pubdate: Option<DateTime<Utc>> = item.pub_date()
+        .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+        .map(|date| date.with_timezone(&Utc));
+
+    Episode {
+        id: None,
+        title: item.title().unwrap_or("").to_string(),
+        url,
+        description: item.description().unwrap_or("").to_string(),
+        pubdate,
+        duration: episode_duration(item),
+        path: None,
+        played: false,
+    }
+}
+
+/// Reads the `<itunes:duration>` value off a feed item, if present, and
+/// parses it into a number of seconds. This is what `spawn_feed_checker`
+/// calls while building each `Episode` from its source `Item`, so the
+/// new `duration` field actually gets populated from feeds rather than
+/// always coming back `None`.
+pub fn episode_duration(item: &Item) -> Option<i32> {
+    item.itunes_ext()
+        .and_then(|ext| ext.duration())
+        .and_then(parse_duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn episode_duration_reads_itunes_extension() {
+        let xml = r#"<item xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+            <title>Episode</title>
+            <itunes:duration>01:02:03</itunes:duration>
+        </item>"#;
+        let item = Item::from_str(xml).unwrap();
+        assert_eq!(episode_duration(&item), Some(3723));
+    }
+
+    #[test]
+    fn episode_duration_missing_extension_is_none() {
+        let xml = "<item><title>Episode</title></item>";
+        let item = Item::from_str(xml).unwrap();
+        assert_eq!(episode_duration(&item), None);
+    }
+
+    #[test]
+    fn parse_duration_clamps_instead_of_overflowing() {
+        // This hours component parses fine as `i64`, but multiplying it
+        // by 3600 overflows `i64` -- must be caught with checked math
+        // rather than panicking (debug) or wrapping (release).
+        assert_eq!(parse_duration("9000000000000000000:00:00"), Some(i32::MAX));
+    }
+
+    #[test]
+    fn episode_from_item_populates_duration() {
+        let xml = r#"<item xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+            <title>Episode</title>
+            <link>https://example.com/ep1</link>
+            <itunes:duration>90</itunes:duration>
+        </item>"#;
+        let item = Item::from_str(xml).unwrap();
+        let episode = episode_from_item(&item);
+        assert_eq!(episode.duration, Some(90));
+        assert_eq!(episode.url, "https://example.com/ep1");
+    }
+}
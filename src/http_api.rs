@@ -0,0 +1,204 @@
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tiny_http::{Method, Response, Server};
+
+use crate::types::Podcast;
+use crate::ui::UiMsg;
+use crate::Message;
+
+/// Tags every response from the control API so callers can tell a
+/// recoverable error (`Failure`, e.g. a bad index) apart from one that
+/// means the request itself made no sense (`Fatal`, e.g. bad JSON).
+#[derive(Serialize)]
+#[serde(tag = "type", content = "content")]
+enum ApiResponse {
+    Success(serde_json::Value),
+    Failure(String),
+    Fatal(String),
+}
+
+impl ApiResponse {
+    fn status(&self) -> u16 {
+        match self {
+            ApiResponse::Success(_) => 200,
+            ApiResponse::Failure(_) => 409,
+            ApiResponse::Fatal(_) => 400,
+        }
+    }
+}
+
+/// Stripped-down view of a `Podcast` for the `GET /podcasts` endpoint --
+/// we don't want to serialize the live episode list (and its lock)
+/// straight off the shared state.
+#[derive(Serialize)]
+struct PodcastSummary {
+    id: Option<i32>,
+    title: String,
+    url: String,
+    num_episodes: usize,
+    num_unplayed: usize,
+}
+
+impl From<&Podcast> for PodcastSummary {
+    fn from(pod: &Podcast) -> Self {
+        PodcastSummary {
+            id: pod.id,
+            title: pod.title.clone(),
+            url: pod.url.clone(),
+            num_episodes: pod.episodes.lock().unwrap().len(),
+            num_unplayed: pod.num_unplayed,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PlayRequest {
+    pod_index: i32,
+    ep_index: i32,
+}
+
+/// Confirms `pod_index` refers to a podcast that currently exists, so a
+/// bad or stale index from an HTTP request gets turned into a `Failure`
+/// response here instead of panicking a `.get(...).unwrap()` in the
+/// main loop.
+fn check_podcast_index(podcast_list: &Arc<Mutex<Vec<Podcast>>>, pod_index: i32) -> Result<(), ApiResponse> {
+    let borrowed = podcast_list.lock().unwrap();
+    match usize::try_from(pod_index).ok().filter(|&i| i < borrowed.len()) {
+        Some(_) => Ok(()),
+        None => Err(ApiResponse::Failure(format!("No podcast at index {}.", pod_index))),
+    }
+}
+
+/// Same as [`check_podcast_index`], but also confirms `ep_index` refers
+/// to an episode of that podcast.
+fn check_episode_index(podcast_list: &Arc<Mutex<Vec<Podcast>>>, pod_index: i32, ep_index: i32) -> Result<(), ApiResponse> {
+    let borrowed = podcast_list.lock().unwrap();
+    let pod = usize::try_from(pod_index).ok().and_then(|i| borrowed.get(i))
+        .ok_or_else(|| ApiResponse::Failure(format!("No podcast at index {}.", pod_index)))?;
+    let num_episodes = pod.episodes.lock().unwrap().len();
+    match usize::try_from(ep_index).ok().filter(|&i| i < num_episodes) {
+        Some(_) => Ok(()),
+        None => Err(ApiResponse::Failure(format!("No episode at index {}.", ep_index))),
+    }
+}
+
+/// Spawns a thread running the control API on `bind_addr:port`. The
+/// server only ever needs a clone of the channel to main and a
+/// reference to the shared podcast list, since the main loop already
+/// serializes every state mutation through `tx_to_main`.
+///
+/// `bind_addr` defaults to `127.0.0.1` in `config.toml`, but can be set
+/// to `0.0.0.0` (or a LAN address) to allow control from other devices,
+/// e.g. a phone or a separate hotkey daemon. There is no authentication
+/// on this API, so binding to anything beyond loopback only makes sense
+/// on a network you trust -- anyone who can reach the port can play,
+/// download, or stop playback.
+pub fn spawn(tx_to_main: mpsc::Sender<Message>, podcast_list: Arc<Mutex<Vec<Podcast>>>, bind_addr: &str, port: u16) {
+    let bind_addr = bind_addr.to_string();
+    thread::spawn(move || {
+        let server = match Server::http((bind_addr.as_str(), port)) {
+            Ok(server) => server,
+            Err(_) => return,
+        };
+
+        for mut request in server.incoming_requests() {
+            let response = handle_request(&mut request, &tx_to_main, &podcast_list);
+            let status = response.status();
+            let body = serde_json::to_string(&response).unwrap();
+            let _ = request.respond(
+                Response::from_string(body).with_status_code(status),
+            );
+        }
+    });
+}
+
+fn handle_request(
+    request: &mut tiny_http::Request,
+    tx_to_main: &mpsc::Sender<Message>,
+    podcast_list: &Arc<Mutex<Vec<Podcast>>>,
+) -> ApiResponse {
+    let method = request.method().clone();
+    let url = request.url().to_string();
+
+    match (method, url.as_str()) {
+        (Method::Get, "/podcasts") => {
+            let podcasts: Vec<PodcastSummary> = podcast_list.lock().unwrap()
+                .iter()
+                .map(PodcastSummary::from)
+                .collect();
+            ApiResponse::Success(json!(podcasts))
+        },
+
+        (Method::Post, "/play") => {
+            let mut body = String::new();
+            if request.as_reader().read_to_string(&mut body).is_err() {
+                return ApiResponse::Fatal("Could not read request body.".to_string());
+            }
+            match serde_json::from_str::<PlayRequest>(&body) {
+                Ok(play) => match check_episode_index(podcast_list, play.pod_index, play.ep_index) {
+                    Ok(()) => {
+                        let _ = tx_to_main.send(Message::Ui(UiMsg::Play(play.pod_index, play.ep_index)));
+                        ApiResponse::Success(json!({ "queued": "play" }))
+                    },
+                    Err(failure) => failure,
+                },
+                Err(err) => ApiResponse::Fatal(format!("Invalid request body: {}", err)),
+            }
+        },
+
+        (Method::Post, "/stop") => {
+            let _ = tx_to_main.send(Message::Ui(UiMsg::Stop));
+            ApiResponse::Success(json!({ "queued": "stop" }))
+        },
+
+        (Method::Post, "/sync") => {
+            let mut body = String::new();
+            if request.as_reader().read_to_string(&mut body).is_err() {
+                return ApiResponse::Fatal("Could not read request body.".to_string());
+            }
+            match serde_json::from_str::<serde_json::Value>(&body) {
+                Ok(val) => match val.get("pod_index").and_then(|v| v.as_i64()) {
+                    Some(pod_index) => {
+                        let pod_index = pod_index as i32;
+                        match check_podcast_index(podcast_list, pod_index) {
+                            Ok(()) => {
+                                let _ = tx_to_main.send(Message::Ui(UiMsg::Sync(pod_index)));
+                                ApiResponse::Success(json!({ "queued": "sync" }))
+                            },
+                            Err(failure) => failure,
+                        }
+                    },
+                    None => ApiResponse::Failure("Missing \"pod_index\".".to_string()),
+                },
+                Err(err) => ApiResponse::Fatal(format!("Invalid request body: {}", err)),
+            }
+        },
+
+        (Method::Post, "/sync_all") => {
+            let _ = tx_to_main.send(Message::Ui(UiMsg::SyncAll));
+            ApiResponse::Success(json!({ "queued": "sync_all" }))
+        },
+
+        (Method::Post, "/download") => {
+            let mut body = String::new();
+            if request.as_reader().read_to_string(&mut body).is_err() {
+                return ApiResponse::Fatal("Could not read request body.".to_string());
+            }
+            match serde_json::from_str::<PlayRequest>(&body) {
+                Ok(dl) => match check_episode_index(podcast_list, dl.pod_index, dl.ep_index) {
+                    Ok(()) => {
+                        let _ = tx_to_main.send(Message::Ui(UiMsg::Download(dl.pod_index, dl.ep_index)));
+                        ApiResponse::Success(json!({ "queued": "download" }))
+                    },
+                    Err(failure) => failure,
+                },
+                Err(err) => ApiResponse::Fatal(format!("Invalid request body: {}", err)),
+            }
+        },
+
+        _ => ApiResponse::Failure(format!("No such endpoint: {}", url)),
+    }
+}
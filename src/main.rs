@@ -9,6 +9,8 @@ mod types;
 mod feeds;
 mod downloads;
 mod play_file;
+mod opml;
+mod http_api;
 
 use crate::types::*;
 use crate::ui::{UI, UiMsg};
@@ -24,6 +26,101 @@ pub enum MainMessage {
     UiTearDown,
 }
 
+/// Keeps a running tally while an OPML import is in progress, so that
+/// the many `FeedMsg`s that come back from checking each subscribed
+/// feed can be collapsed into a single summary message once they've
+/// all reported in, rather than one message window per feed.
+struct ImportTracker {
+    total: usize,
+    added: usize,
+    skipped: usize,
+}
+
+impl ImportTracker {
+    fn is_complete(&self) -> bool {
+        self.added + self.skipped >= self.total
+    }
+
+    fn summary(&self) -> String {
+        format!("Imported {} podcasts ({} skipped).", self.added, self.skipped)
+    }
+}
+
+/// Figures out where the config file lives by default, for the current
+/// OS, if the user hasn't specified one explicitly.
+fn default_config_path() -> PathBuf {
+    match dirs::config_dir() {
+        Some(mut path) => {
+            path.push("shellcaster");
+            path.push("config.toml");
+            path
+        },
+        None => panic!("Could not identify your operating system's default directory to store configuration files. Please specify paths manually using config.toml and use `-c` or `--config` flag to specify where config.toml is located when launching the program."),
+    }
+}
+
+/// Handles `shellcaster import <file>`: reads the feeds out of an OPML
+/// file, checks each one, and adds any new podcasts to the database.
+/// This runs synchronously and exits when done, rather than starting
+/// up the full UI.
+fn run_import(file: &PathBuf) {
+    let feeds = match opml::import(file) {
+        Ok(feeds) => feeds,
+        Err(err) => {
+            eprintln!("Error reading OPML file: {}", err);
+            std::process::exit(1);
+        },
+    };
+    if feeds.is_empty() {
+        println!("No feeds found in {}.", file.display());
+        return;
+    }
+
+    let config = config::parse_config_file(&default_config_path());
+    let db_inst = Database::connect(&config.config_path);
+
+    if db_inst.get_offline_mode() {
+        println!("Cannot import feeds while offline.");
+        return;
+    }
+
+    let (tx_to_main, rx_to_main) = mpsc::channel();
+
+    for feed in feeds.iter() {
+        let tx_feeds_to_main = mpsc::Sender::clone(&tx_to_main);
+        let _ = feeds::spawn_feed_checker(tx_feeds_to_main, feed.url.clone(), None);
+    }
+
+    let mut added = 0;
+    let mut skipped = 0;
+    for message in rx_to_main.iter().take(feeds.len()) {
+        match message {
+            Message::Feed(FeedMsg::NewData(pod)) => match db_inst.insert_podcast(pod) {
+                Ok(_) => added += 1,
+                Err(_) => skipped += 1,
+            },
+            Message::Feed(FeedMsg::Error) => skipped += 1,
+            _ => (),
+        }
+    }
+    println!("Imported {} podcasts ({} skipped).", added, skipped);
+}
+
+/// Handles `shellcaster export <file>`: writes out every subscribed
+/// podcast as an OPML document, then exits.
+fn run_export(file: &PathBuf) {
+    let config = config::parse_config_file(&default_config_path());
+    let db_inst = Database::connect(&config.config_path);
+    let podcasts = db_inst.get_podcasts();
+    match opml::export(&podcasts, file) {
+        Ok(_) => println!("Exported {} podcasts to {}.", podcasts.len(), file.display()),
+        Err(err) => {
+            eprintln!("Error writing OPML file: {}", err);
+            std::process::exit(1);
+        },
+    }
+}
+
 /// Main controller for shellcaster program.
 /// 
 /// Setup involves connecting to the sqlite database (creating it if 
@@ -40,20 +137,25 @@ fn main() {
     // command line args, or using default config location for OS
     let mut config_path;
     let args: Vec<String> = std::env::args().collect();
+    match args.get(1).map(|s| s.as_str()) {
+        Some("import") => {
+            let file = args.get(2).unwrap_or_else(|| panic!("Usage: shellcaster import <file>"));
+            run_import(&PathBuf::from(file));
+            return;
+        },
+        Some("export") => {
+            let file = args.get(2).unwrap_or_else(|| panic!("Usage: shellcaster export <file>"));
+            run_export(&PathBuf::from(file));
+            return;
+        },
+        _ => (),
+    }
     match args.len() {
         3 => {
             config_path = PathBuf::from(&args[2]);
         },
         _ => {
-            let default_config = dirs::config_dir();
-            match default_config {
-                Some(path) => {
-                    config_path = path;
-                    config_path.push("shellcaster");
-                    config_path.push("config.toml");
-                },
-                None => panic!("Could not identify your operating system's default directory to store configuration files. Please specify paths manually using config.toml and use `-c` or `--config` flag to specify where config.toml is located when launching the program."),
-            } 
+            config_path = default_config_path();
         }
     }
     let config = config::parse_config_file(&config_path);
@@ -77,15 +179,88 @@ fn main() {
     let ui_thread = UI::spawn(config.clone(), Arc::clone(&podcast_list), rx_from_main, tx_ui_to_main);
         // TODO: Can we do this without cloning the config?
 
+    if config.http_api_enabled {
+        let tx_api_to_main = mpsc::Sender::clone(&tx_to_main);
+        http_api::spawn(tx_api_to_main, Arc::clone(&podcast_list),
+            &config.http_api_bind_addr, config.http_api_port);
+    }
+
+    let mut import_tracker: Option<ImportTracker> = None;
+
+    // Handle to whatever `play_file::execute` last spawned, so a `Stop`
+    // message (from a keybinding or the HTTP control API) has something
+    // to kill. `None` until something has played, and again after a
+    // `Stop`; a player that exits on its own is not cleared here.
+    let mut current_player: Option<std::process::Child> = None;
+
+    // Offline mode disables everything that needs the network (adding
+    // feeds, syncing, downloading) and restricts playback to episodes
+    // that have already been downloaded. The setting is stored in the
+    // database so it persists between runs.
+    let mut offline = db_inst.get_offline_mode();
+
     let mut message_iter = rx_to_main.iter();
     loop {
         if let Some(message) = message_iter.next() {
             match message {
                 Message::Ui(UiMsg::Quit) => break,
 
+                Message::Ui(UiMsg::ToggleOffline) => {
+                    offline = !offline;
+                    db_inst.set_offline_mode(offline);
+                    let msg = if offline {
+                        "Offline mode enabled.".to_string()
+                    } else {
+                        "Offline mode disabled.".to_string()
+                    };
+                    tx_to_ui.send(MainMessage::UiSpawnMsgWin(msg, 5000)).unwrap();
+                    if !offline {
+                        // back online -- refresh feeds right away
+                        tx_to_main.send(Message::Ui(UiMsg::SyncAll)).unwrap();
+                    }
+                },
+
                 Message::Ui(UiMsg::AddFeed(url)) => {
-                    let tx_feeds_to_main = mpsc::Sender::clone(&tx_to_main);
-                    let _ = feeds::spawn_feed_checker(tx_feeds_to_main, url, None);
+                    if offline {
+                        tx_to_ui.send(MainMessage::UiSpawnMsgWin(
+                            "Cannot add feeds while offline.".to_string(), 5000)).unwrap();
+                    } else {
+                        let tx_feeds_to_main = mpsc::Sender::clone(&tx_to_main);
+                        let _ = feeds::spawn_feed_checker(tx_feeds_to_main, url, None);
+                    }
+                },
+
+                Message::Ui(UiMsg::ImportOpml(path)) => {
+                    if offline {
+                        tx_to_ui.send(MainMessage::UiSpawnMsgWin(
+                            "Cannot import feeds while offline.".to_string(), 5000)).unwrap();
+                    } else {
+                        match opml::import(&path) {
+                            Ok(feeds) if feeds.is_empty() => tx_to_ui.send(MainMessage::UiSpawnMsgWin(
+                                "No feeds found in OPML file.".to_string(), 5000)).unwrap(),
+                            Ok(feeds) => {
+                                import_tracker = Some(ImportTracker {
+                                    total: feeds.len(), added: 0, skipped: 0,
+                                });
+                                for feed in feeds {
+                                    let tx_feeds_to_main = mpsc::Sender::clone(&tx_to_main);
+                                    let _ = feeds::spawn_feed_checker(tx_feeds_to_main, feed.url, None);
+                                }
+                            },
+                            Err(_err) => tx_to_ui.send(MainMessage::UiSpawnMsgWin(
+                                "Error reading OPML file.".to_string(), 5000)).unwrap(),
+                        }
+                    }
+                },
+
+                Message::Ui(UiMsg::ExportOpml(path)) => {
+                    let borrowed_pod_list = podcast_list.lock().unwrap();
+                    match opml::export(&borrowed_pod_list, &path) {
+                        Ok(_) => tx_to_ui.send(MainMessage::UiSpawnMsgWin(
+                            format!("Exported {} podcasts.", borrowed_pod_list.len()), 5000)).unwrap(),
+                        Err(_err) => tx_to_ui.send(MainMessage::UiSpawnMsgWin(
+                            "Error exporting OPML file.".to_string(), 5000)).unwrap(),
+                    }
                 },
 
                 Message::Feed(FeedMsg::NewData(pod)) => {
@@ -93,27 +268,57 @@ fn main() {
                         Ok(num_ep) => {
                             *podcast_list.lock().unwrap() = db_inst.get_podcasts();
                             tx_to_ui.send(MainMessage::UiUpdateMenus).unwrap();
-                            tx_to_ui.send(MainMessage::UiSpawnMsgWin(format!("Successfully added {} episodes.", num_ep), 5000)).unwrap();
+                            match import_tracker.as_mut() {
+                                Some(tracker) => tracker.added += 1,
+                                None => tx_to_ui.send(MainMessage::UiSpawnMsgWin(format!("Successfully added {} episodes.", num_ep), 5000)).unwrap(),
+                            }
                         },
-                        Err(_err) => tx_to_ui.send(MainMessage::UiSpawnMsgWin("Error adding podcast to database.".to_string(), 5000)).unwrap(),
+                        Err(_err) => {
+                            match import_tracker.as_mut() {
+                                Some(tracker) => tracker.skipped += 1,
+                                None => tx_to_ui.send(MainMessage::UiSpawnMsgWin("Error adding podcast to database.".to_string(), 5000)).unwrap(),
+                            }
+                        },
+                    }
+                    if let Some(tracker) = &import_tracker {
+                        if tracker.is_complete() {
+                            tx_to_ui.send(MainMessage::UiSpawnMsgWin(tracker.summary(), 5000)).unwrap();
+                            import_tracker = None;
+                        }
                     }
                 },
 
-                Message::Feed(FeedMsg::Error) => tx_to_ui.send(MainMessage::UiSpawnMsgWin(
-                    "Error retrieving RSS feed.".to_string(), 5000)).unwrap(),
+                Message::Feed(FeedMsg::Error) => {
+                    match import_tracker.as_mut() {
+                        Some(tracker) => tracker.skipped += 1,
+                        None => tx_to_ui.send(MainMessage::UiSpawnMsgWin(
+                            "Error retrieving RSS feed.".to_string(), 5000)).unwrap(),
+                    }
+                    if let Some(tracker) = &import_tracker {
+                        if tracker.is_complete() {
+                            tx_to_ui.send(MainMessage::UiSpawnMsgWin(tracker.summary(), 5000)).unwrap();
+                            import_tracker = None;
+                        }
+                    }
+                },
 
                 Message::Ui(UiMsg::Sync(pod_index)) => {
-                    let url;
-                    let id;
-                    {
-                        let borrowed_pod_list = podcast_list.lock().unwrap();
-                        let borrowed_podcast = borrowed_pod_list
-                            .get(pod_index as usize).unwrap();
-                        url = borrowed_podcast.url.clone();
-                        id = borrowed_podcast.id;
+                    if offline {
+                        tx_to_ui.send(MainMessage::UiSpawnMsgWin(
+                            "Cannot sync while offline.".to_string(), 5000)).unwrap();
+                    } else {
+                        let url;
+                        let id;
+                        {
+                            let borrowed_pod_list = podcast_list.lock().unwrap();
+                            let borrowed_podcast = borrowed_pod_list
+                                .get(pod_index as usize).unwrap();
+                            url = borrowed_podcast.url.clone();
+                            id = borrowed_podcast.id;
+                        }
+                        let tx_feeds_to_main = mpsc::Sender::clone(&tx_to_main);
+                        let _ = feeds::spawn_feed_checker(tx_feeds_to_main, url, id);
                     }
-                    let tx_feeds_to_main = mpsc::Sender::clone(&tx_to_main);
-                    let _ = feeds::spawn_feed_checker(tx_feeds_to_main, url, id);
                 },
 
                 Message::Feed(FeedMsg::SyncData(pod)) => {
@@ -131,24 +336,29 @@ fn main() {
                 },
 
                 Message::Ui(UiMsg::SyncAll) => {
-                    // We pull out the data we need here first, so we can
-                    // stop borrowing the podcast list as quickly as possible.
-                    // Slightly less efficient (two loops instead of
-                    // one), but then it won't block other tasks that
-                    // need to access the list.
-                    let mut pod_data = Vec::new();
-                    {
-                        let borrowed_pod_list = podcast_list.lock().unwrap();
-                        for podcast in borrowed_pod_list.iter() {
-                            pod_data.push((podcast.url.clone(), podcast.id));
+                    if offline {
+                        tx_to_ui.send(MainMessage::UiSpawnMsgWin(
+                            "Cannot sync while offline.".to_string(), 5000)).unwrap();
+                    } else {
+                        // We pull out the data we need here first, so we can
+                        // stop borrowing the podcast list as quickly as possible.
+                        // Slightly less efficient (two loops instead of
+                        // one), but then it won't block other tasks that
+                        // need to access the list.
+                        let mut pod_data = Vec::new();
+                        {
+                            let borrowed_pod_list = podcast_list.lock().unwrap();
+                            for podcast in borrowed_pod_list.iter() {
+                                pod_data.push((podcast.url.clone(), podcast.id));
+                            }
                         }
-                    }
-                    for data in pod_data.iter() {
-                        let url = data.0.clone();
-                        let id = data.1;
+                        for data in pod_data.iter() {
+                            let url = data.0.clone();
+                            let id = data.1;
 
-                        let tx_feeds_to_main = mpsc::Sender::clone(&tx_to_main);
-                        let _ = feeds::spawn_feed_checker(tx_feeds_to_main, url, id);
+                            let tx_feeds_to_main = mpsc::Sender::clone(&tx_to_main);
+                            let _ = feeds::spawn_feed_checker(tx_feeds_to_main, url, id);
+                        }
                     }
                 },
 
@@ -166,59 +376,71 @@ fn main() {
                             .get(ep_index as usize).unwrap().clone();
                     }
 
-                    match episode.path {
-                        Some(path) => {
-                            match path.to_str() {
-                                Some(p) => {
-                                    if play_file::execute(&config.play_command, &p).is_err() {
-                                        tx_to_ui.send(MainMessage::UiSpawnMsgWin(
-                                            "Error: Could not play file. Check configuration.".to_string(), 5000)).unwrap();
-                                    }
-                                },
-                                None => tx_to_ui.send(MainMessage::UiSpawnMsgWin(
-                                    "Error: Filepath is not valid Unicode.".to_string(), 5000)).unwrap(),
-                            }
-                        },
-                        None => {
-                            if play_file::execute(&config.play_command, &episode.url).is_err() {
-                                tx_to_ui.send(MainMessage::UiSpawnMsgWin(
-                                    "Error: Could not stream URL.".to_string(), 5000)).unwrap();
+                    if offline && episode.path.is_none() {
+                        tx_to_ui.send(MainMessage::UiSpawnMsgWin(
+                            "Cannot stream while offline: episode has not been downloaded.".to_string(), 5000)).unwrap();
+                    } else {
+                        match episode.path {
+                            Some(path) => {
+                                match path.to_str() {
+                                    Some(p) => {
+                                        match play_file::execute(&config.play_command, &p) {
+                                            Ok(child) => current_player = Some(child),
+                                            Err(_) => tx_to_ui.send(MainMessage::UiSpawnMsgWin(
+                                                "Error: Could not play file. Check configuration.".to_string(), 5000)).unwrap(),
+                                        }
+                                    },
+                                    None => tx_to_ui.send(MainMessage::UiSpawnMsgWin(
+                                        "Error: Filepath is not valid Unicode.".to_string(), 5000)).unwrap(),
+                                }
+                            },
+                            None => {
+                                match play_file::execute(&config.play_command, &episode.url) {
+                                    Ok(child) => current_player = Some(child),
+                                    Err(_) => tx_to_ui.send(MainMessage::UiSpawnMsgWin(
+                                        "Error: Could not stream URL.".to_string(), 5000)).unwrap(),
+                                }
                             }
                         }
                     }
                 },
 
+                Message::Ui(UiMsg::Stop) => {
+                    if let Some(mut child) = current_player.take() {
+                        let _ = child.kill();
+                    }
+                },
+
                 Message::Ui(UiMsg::MarkPlayed(pod_index, ep_index, played)) => {
                     let mut borrowed_pod_list = podcast_list.lock().unwrap();
                     // TODO: Try to find a way to do this without having
                     // to clone the podcast...
                     let mut podcast = borrowed_pod_list
                         .get(pod_index as usize).unwrap().clone();
-                    let mut any_unplayed = false;
+                    let mut num_unplayed = 0;
                     {
                         let mut borrowed_ep_list = podcast
                             .episodes.lock().unwrap();
-                        
+
                         // TODO: Try to find a way to do this without having
                         // to clone the episode...
                         let mut episode = borrowed_ep_list
                             .get(ep_index as usize).unwrap().clone();
                         episode.played = played;
-                        
+
                         db_inst.set_played_status(episode.id.unwrap(), played);
                         borrowed_ep_list[ep_index as usize] = episode;
 
-                        // recheck if there are any unplayed episodes for the
+                        // recount how many unplayed episodes remain for the
                         // selected podcast
                         for ep in borrowed_ep_list.iter() {
                             if !ep.played {
-                                any_unplayed = true;
-                                break;
+                                num_unplayed += 1;
                             }
                         }
                     }
-                    if any_unplayed != podcast.any_unplayed {
-                        podcast.any_unplayed = any_unplayed;
+                    if num_unplayed != podcast.num_unplayed {
+                        podcast.num_unplayed = num_unplayed;
                         borrowed_pod_list[pod_index as usize] = podcast;
                     }
                 },
@@ -240,32 +462,37 @@ fn main() {
                         *borrowed_ep_list = db_inst.get_episodes(podcast.id.unwrap());
                     }
 
-                    podcast.any_unplayed = !played;
+                    podcast.num_unplayed = if played { 0 } else { podcast.episodes.lock().unwrap().len() };
                     borrowed_pod_list[pod_index as usize] = podcast;
                     tx_to_ui.send(MainMessage::UiUpdateMenus).unwrap();
                 },
 
                 Message::Ui(UiMsg::Download(pod_index, ep_index)) => {
-                    let borrowed_pod_list = podcast_list.lock().unwrap();
-                    let borrowed_podcast = borrowed_pod_list
-                        .get(pod_index as usize).unwrap();
-                    let borrowed_ep_list = borrowed_podcast
-                        .episodes.lock().unwrap();
-                    // TODO: Try to find a way to do this without having
-                    // to clone the episode...
-                    let episode = borrowed_ep_list
-                        .get(ep_index as usize).unwrap().clone();
-
-                    // add directory for podcast, create if it does not exist
-                    let mut download_path = config.download_path.clone();
-                    download_path.push(borrowed_podcast.title.clone());
-                    if std::fs::create_dir_all(&download_path).is_err() {
+                    if offline {
                         tx_to_ui.send(MainMessage::UiSpawnMsgWin(
-                            format!("Could not create dir: {}", borrowed_podcast.title.clone()), 5000)).unwrap();
-                    }
+                            "Cannot download while offline.".to_string(), 5000)).unwrap();
+                    } else {
+                        let borrowed_pod_list = podcast_list.lock().unwrap();
+                        let borrowed_podcast = borrowed_pod_list
+                            .get(pod_index as usize).unwrap();
+                        let borrowed_ep_list = borrowed_podcast
+                            .episodes.lock().unwrap();
+                        // TODO: Try to find a way to do this without having
+                        // to clone the episode...
+                        let episode = borrowed_ep_list
+                            .get(ep_index as usize).unwrap().clone();
 
-                    download_manager.download_list(
-                        &[&episode], &download_path);
+                        // add directory for podcast, create if it does not exist
+                        let mut download_path = config.download_path.clone();
+                        download_path.push(borrowed_podcast.title.clone());
+                        if std::fs::create_dir_all(&download_path).is_err() {
+                            tx_to_ui.send(MainMessage::UiSpawnMsgWin(
+                                format!("Could not create dir: {}", borrowed_podcast.title.clone()), 5000)).unwrap();
+                        }
+
+                        download_manager.download_list(
+                            &[&episode], &download_path);
+                    }
                 },
 
                 Message::Dl(DownloadMsg::Complete(ep_data)) => {
@@ -306,31 +533,36 @@ fn main() {
                 },
 
                 Message::Ui(UiMsg::DownloadAll(pod_index)) => {
-                    let borrowed_pod_list = podcast_list.lock().unwrap();
-                    let borrowed_podcast = borrowed_pod_list
-                        .get(pod_index as usize).unwrap();
-                    let borrowed_ep_list = borrowed_podcast
-                        .episodes.lock().unwrap();
+                    if offline {
+                        tx_to_ui.send(MainMessage::UiSpawnMsgWin(
+                            "Cannot download while offline.".to_string(), 5000)).unwrap();
+                    } else {
+                        let borrowed_pod_list = podcast_list.lock().unwrap();
+                        let borrowed_podcast = borrowed_pod_list
+                            .get(pod_index as usize).unwrap();
+                        let borrowed_ep_list = borrowed_podcast
+                            .episodes.lock().unwrap();
 
-                    // TODO: Try to find a way to do this without having
-                    // to clone the episodes...
-                    let mut episodes = Vec::new();
-                    let mut episode_refs = Vec::new();
-                    for e in borrowed_ep_list.iter() {
-                        episodes.push(e.clone());
-                        episode_refs.push(e);
-                    }
+                        // TODO: Try to find a way to do this without having
+                        // to clone the episodes...
+                        let mut episodes = Vec::new();
+                        let mut episode_refs = Vec::new();
+                        for e in borrowed_ep_list.iter() {
+                            episodes.push(e.clone());
+                            episode_refs.push(e);
+                        }
 
-                    // add directory for podcast, create if it does not exist
-                    let mut download_path = config.download_path.clone();
-                    download_path.push(borrowed_podcast.title.clone());
-                    if std::fs::create_dir_all(&download_path).is_err() {
-                        tx_to_ui.send(MainMessage::UiSpawnMsgWin(
-                            format!("Could not create dir: {}", borrowed_podcast.title.clone()), 5000)).unwrap();
-                    }
+                        // add directory for podcast, create if it does not exist
+                        let mut download_path = config.download_path.clone();
+                        download_path.push(borrowed_podcast.title.clone());
+                        if std::fs::create_dir_all(&download_path).is_err() {
+                            tx_to_ui.send(MainMessage::UiSpawnMsgWin(
+                                format!("Could not create dir: {}", borrowed_podcast.title.clone()), 5000)).unwrap();
+                        }
 
-                    download_manager.download_list(
-                        &episode_refs, &download_path);
+                        download_manager.download_list(
+                            &episode_refs, &download_path);
+                    }
                 },
 
                 Message::Ui(UiMsg::Noop) => (),
@@ -0,0 +1,90 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use crate::types::Podcast;
+
+/// Represents a single feed found while parsing an OPML file. We only
+/// pull out the bits we actually need to add the feed -- everything
+/// else (folders, categories, etc.) is ignored.
+#[derive(Debug, Clone)]
+pub struct OpmlFeed {
+    pub title: Option<String>,
+    pub url: String,
+}
+
+/// Parses an OPML file at `path` and returns every feed it finds, by
+/// reading the `xmlUrl` attribute off of each `<outline>` element.
+/// Outlines that don't represent a feed (e.g., folders used purely for
+/// grouping) won't have an `xmlUrl` attribute, and are skipped.
+///
+/// Returns an error if the file can't be read, or if the XML itself is
+/// malformed partway through -- we'd rather report a truncated/corrupt
+/// file than silently import only the feeds parsed before the error.
+pub fn import(path: &Path) -> Result<Vec<OpmlFeed>, io::Error> {
+    let file = fs::read_to_string(path)?;
+    let mut reader = Reader::from_str(&file);
+    reader.trim_text(true);
+
+    let mut feeds = Vec::new();
+    let mut buf = Vec::new();
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Empty(ref e)) | Ok(Event::Start(ref e)) if e.name().as_ref() == b"outline" => {
+                if let Some(feed) = outline_to_feed(e) {
+                    feeds.push(feed);
+                }
+            },
+            Ok(Event::Eof) => break,
+            Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+            _ => (),
+        }
+        buf.clear();
+    }
+    Ok(feeds)
+}
+
+fn outline_to_feed(tag: &BytesStart) -> Option<OpmlFeed> {
+    let mut url = None;
+    let mut title = None;
+    for attr in tag.attributes().flatten() {
+        let key = attr.key.as_ref();
+        if key == b"xmlUrl" {
+            url = Some(attr.unescape_value().ok()?.into_owned());
+        } else if title.is_none() && (key == b"text" || key == b"title") {
+            // Unlike `xmlUrl`, a malformed `text`/`title` (or any other
+            // attribute some other podcast app's export happens to
+            // include) shouldn't sink the whole feed -- just skip it.
+            if let Ok(value) = attr.unescape_value() {
+                title = Some(value.into_owned());
+            }
+        }
+    }
+    url.map(|url| OpmlFeed { title, url })
+}
+
+/// Writes out an OPML document containing every podcast in `podcasts`,
+/// so the list of subscriptions can be imported into another podcast
+/// app.
+pub fn export(podcasts: &[Podcast], path: &Path) -> Result<(), io::Error> {
+    let mut out = String::new();
+    out.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<opml version=\"2.0\">\n");
+    out.push_str("  <head>\n    <title>shellcaster subscriptions</title>\n  </head>\n");
+    out.push_str("  <body>\n");
+    for podcast in podcasts {
+        out.push_str(&format!(
+            "    <outline type=\"rss\" text=\"{}\" xmlUrl=\"{}\"/>\n",
+            escape(&podcast.title),
+            escape(&podcast.url)
+        ));
+    }
+    out.push_str("  </body>\n</opml>\n");
+
+    let mut file = fs::File::create(path)?;
+    file.write_all(out.as_bytes())
+}
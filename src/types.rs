@@ -3,11 +3,39 @@ use std::rc::Rc;
 use std::ops::{Bound, RangeBounds};
 use core::cell::RefCell;
 use chrono::{DateTime, Utc};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Minimum number of columns needed before a podcast row grows an
+/// "(unplayed/total)" count.
+const MIN_WIDTH_FOR_COUNTS: usize = 25;
+
+/// Minimum number of columns needed before an episode row grows a
+/// duration column.
+const MIN_WIDTH_FOR_DURATION: usize = 45;
+
+/// Minimum number of columns needed before an episode row also grows
+/// a pubdate column.
+const MIN_WIDTH_FOR_PUBDATE: usize = 60;
 
 /// Defines interface used for both podcasts and episodes, to be
 /// used and displayed in menus.
 pub trait Menuable {
-    fn get_title(&self, length: usize) -> String;
+    fn get_title(&self, width: usize) -> String;
+}
+
+/// Lays out a single menu row: `title` is truncated or padded to
+/// take up the rest of `width` once `meta` (already formatted, e.g.
+/// `"(3/10)"`) has been reserved, so `meta` stays right-aligned no
+/// matter how long the individual titles in a menu are.
+fn format_row(title: &str, meta: &str, width: usize) -> String {
+    if meta.is_empty() {
+        return title.substring(0, width).to_string();
+    }
+    let title_width = width.saturating_sub(meta.grapheme_width() + 1);
+    let truncated = title.substring(0, title_width);
+    let pad = " ".repeat(title_width.saturating_sub(truncated.grapheme_width()));
+    format!("{}{} {}", truncated, pad, meta)
 }
 
 /// Struct holding data about an individual podcast feed. This includes a
@@ -22,11 +50,22 @@ pub struct Podcast {
     pub explicit: Option<bool>,
     pub last_checked: DateTime<Utc>,
     pub episodes: MutableVec<Episode>,
+    /// Number of episodes that have not yet been marked played. Kept
+    /// up to date whenever an episode's played status changes, so
+    /// menus don't have to rescan the whole episode list to render
+    /// a row.
+    pub num_unplayed: usize,
 }
 
 impl Menuable for Podcast {
-    fn get_title(&self, length: usize) -> String {
-        return self.title[..].substring(0, length).to_string();
+    fn get_title(&self, width: usize) -> String {
+        let total = self.episodes.lock().unwrap().len();
+        let meta = if width >= MIN_WIDTH_FOR_COUNTS {
+            format!("({}/{})", self.num_unplayed, total)
+        } else {
+            String::new()
+        };
+        format_row(&self.title, &meta, width)
     }
 }
 
@@ -46,12 +85,37 @@ pub struct Episode {
     pub played: bool,
 }
 
+impl Episode {
+    /// Formats `duration` (in seconds) as `HH:MM:SS` for display,
+    /// returning `None` if the episode has no known duration.
+    pub fn format_duration(&self) -> Option<String> {
+        self.duration.map(|secs| {
+            let secs = secs.max(0);
+            format!("{:02}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
+        })
+    }
+}
+
 impl Menuable for Episode {
-    fn get_title(&self, length: usize) -> String {
-        return match self.path {
-            Some(_) => format!("[D] {}", self.title[..].substring(0, length-4)),
-            None => self.title[..].substring(0, length).to_string(),
+    fn get_title(&self, width: usize) -> String {
+        let mut meta_parts = Vec::new();
+        if width >= MIN_WIDTH_FOR_DURATION {
+            if let Some(duration) = self.format_duration() {
+                meta_parts.push(duration);
+            }
+        }
+        if width >= MIN_WIDTH_FOR_PUBDATE {
+            if let Some(pubdate) = self.pubdate {
+                meta_parts.push(pubdate.format("%Y-%m-%d").to_string());
+            }
+        }
+        let meta = meta_parts.join(" ");
+
+        let title = match self.path {
+            Some(_) => format!("[D] {}", self.title),
+            None => self.title.clone(),
         };
+        format_row(&title, &meta, width)
     }
 }
 
@@ -61,41 +125,57 @@ pub type MutableVec<T> = Rc<RefCell<Vec<T>>>;
 
 
 
-// some utilities for dealing with UTF-8 substrings that split properly
-// on character boundaries. From:
-// https://users.rust-lang.org/t/how-to-get-a-substring-of-a-string/1351/11
-// Note that using UnicodeSegmentation::graphemes() from the
-// `unicode-segmentation` crate might still end up being preferable...
+// Utilities for dealing with substrings that split properly on
+// grapheme cluster boundaries (so combining marks and emoji-modifier
+// sequences stay intact) and that measure length in terminal display
+// columns rather than chars (so double-width CJK/emoji are accounted
+// for correctly). `start` and `len`/the range bounds are all in
+// columns, not chars or bytes.
 pub trait StringUtils {
+    /// Display width of the whole string, in terminal columns.
+    fn grapheme_width(&self) -> usize;
     fn substring(&self, start: usize, len: usize) -> &str;
     fn slice(&self, range: impl RangeBounds<usize>) -> &str;
 }
 
 impl StringUtils for str {
+    fn grapheme_width(&self) -> usize {
+        self.width()
+    }
+
     fn substring(&self, start: usize, len: usize) -> &str {
-        let mut char_pos = 0;
-        let mut byte_start = 0;
-        let mut it = self.chars();
-        loop {
-            if char_pos == start { break; }
-            if let Some(c) = it.next() {
-                char_pos += 1;
-                byte_start += c.len_utf8();
+        let mut graphemes = self.grapheme_indices(true).peekable();
+
+        // skip past the first `start` columns
+        let mut col = 0;
+        let mut byte_start = self.len();
+        while let Some(&(byte_idx, grapheme)) = graphemes.peek() {
+            if col >= start {
+                byte_start = byte_idx;
+                break;
             }
-            else { break; }
+            col += grapheme.width();
+            graphemes.next();
         }
-        char_pos = 0;
-        let mut byte_end = byte_start;
-        loop {
-            if char_pos == len { break; }
-            if let Some(c) = it.next() {
-                char_pos += 1;
-                byte_end += c.len_utf8();
+        if graphemes.peek().is_none() {
+            return "";
+        }
+
+        // take graphemes until adding the next one would exceed `len`
+        // columns, so a double-width grapheme is never split
+        let mut taken = 0;
+        let mut byte_end = self.len();
+        for (byte_idx, grapheme) in graphemes {
+            let w = grapheme.width();
+            if taken + w > len {
+                byte_end = byte_idx;
+                break;
             }
-            else { break; }
+            taken += w;
         }
         &self[byte_start..byte_end]
     }
+
     fn slice(&self, range: impl RangeBounds<usize>) -> &str {
         let start = match range.start_bound() {
             Bound::Included(bound) | Bound::Excluded(bound) => *bound,
@@ -104,8 +184,58 @@ impl StringUtils for str {
         let len = match range.end_bound() {
             Bound::Included(bound) => *bound + 1,
             Bound::Excluded(bound) => *bound,
-            Bound::Unbounded => self.len(),
+            Bound::Unbounded => self.grapheme_width(),
         } - start;
         self.substring(start, len)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substring_plain_ascii() {
+        assert_eq!("hello world".substring(0, 5), "hello");
+    }
+
+    #[test]
+    fn substring_combining_accent_not_split() {
+        // "e" + combining acute accent (U+0301) is a single grapheme
+        let text = "cafe\u{0301} au lait";
+        assert_eq!(text.substring(0, 4), "cafe\u{0301}");
+    }
+
+    #[test]
+    fn substring_cjk_counts_double_width() {
+        // each character here has display width 2
+        let text = "测试测试";
+        assert_eq!(text.substring(0, 4), "测试");
+        // a width that splits a wide char mid-way should back off
+        // rather than overflow the budget
+        assert_eq!(text.substring(0, 3), "测");
+    }
+
+    #[test]
+    fn substring_emoji_with_modifier_not_split() {
+        // thumbs up + medium skin tone modifier is a single grapheme
+        let emoji = "\u{1F44D}\u{1F3FD}";
+        let text = format!("{} nice", emoji);
+
+        // given enough room, the whole string comes through untouched
+        assert_eq!(text.substring(0, text.grapheme_width()), text.as_str());
+
+        // given too little room to fit the emoji, it must be dropped
+        // entirely rather than cut into an invalid partial sequence
+        let emoji_width = emoji.grapheme_width();
+        if emoji_width > 1 {
+            assert_eq!(text.substring(0, emoji_width - 1), "");
+        }
+    }
+
+    #[test]
+    fn grapheme_width_counts_wide_chars() {
+        assert_eq!("ab".grapheme_width(), 2);
+        assert_eq!("测试".grapheme_width(), 4);
+    }
 }
\ No newline at end of file